@@ -1,48 +1,119 @@
 use std::{
     fs,
-    path::PathBuf,
-    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
     collections::BTreeMap,
 };
 
 use oneshot::{OneSet,OneGet};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
-pub struct FileEvent<T> {
+pub struct FileEvent<T, D = ()> {
     pub opaque: T,
-    pub content: FileContent,
+    pub content: FileContent<D>,
+    pub kind: ChangeKind,
 }
 
-#[derive(Debug,Clone)]
-pub enum FileContent {
-    Text(String),
+/// How a watched path's state transitioned, so consumers can distinguish a
+/// fresh creation from an in-place edit (e.g. to run different logic on
+/// initial load versus hot-reload).
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ChangeKind {
+    Create,
+    Write,
     Remove,
+}
+
+#[derive(Debug,Clone)]
+pub enum FileContent<D = ()> {
+    /// The current state of a watched text file: `Some(text)` when present,
+    /// `None` when absent. Absence is represented explicitly so that one value
+    /// fully describes the file, independent of any intermediate transitions
+    /// that were coalesced away.
+    ///
+    /// Coalescing guarantee: in a quiescent state, replaying the latest
+    /// delivered `FileEvent` for each path reconstructs exactly what is on disk
+    /// now. Bursts for the same path are collapsed before the file is read, so
+    /// a create-then-delete yields only the final absence and a write-then-write
+    /// yields only the newest contents.
+    ///
+    /// The guarantee covers tracked paths only. A path that is absent when it
+    /// is passed to `add_watch` is not tracked, so its later creation produces
+    /// no event. Without the notify backend, files created under a directory
+    /// root after its initial scan are not discovered.
+    SingleFile(Option<String>),
+    /// The current state of a watched binary file, present or absent.
+    Bytes(Option<Vec<u8>>),
+    /// A value produced by a `FileType::Custom` decoder, present or absent; lets
+    /// callers receive already-deserialized configuration instead of re-parsing
+    /// raw text on every reload.
+    Decoded(Option<D>),
     Error(String),
 }
-impl From<std::io::Error> for FileContent {
-    fn from(e: std::io::Error) -> FileContent {
+impl<D> FileContent<D> {
+    fn is_present(&self) -> bool {
+        matches!(self,
+            FileContent::SingleFile(Some(_)) |
+            FileContent::Bytes(Some(_)) |
+            FileContent::Decoded(Some(_)))
+    }
+    fn is_absent(&self) -> bool {
+        matches!(self,
+            FileContent::SingleFile(None) |
+            FileContent::Bytes(None) |
+            FileContent::Decoded(None))
+    }
+}
+impl<D> From<std::io::Error> for FileContent<D> {
+    fn from(e: std::io::Error) -> FileContent<D> {
         FileContent::Error(e.to_string())
     }
 }
 
-#[derive(Clone,Copy)]
-pub enum FileType {
+/// How a watched file's bytes are decoded before being delivered. `Custom`
+/// carries a parser run on the watcher thread, so a JSON/TOML/YAML config is
+/// deserialized once per change rather than by every consumer.
+#[derive(Clone)]
+pub enum FileType<D = ()> {
     Text,
+    Bytes,
+    Custom(Arc<dyn Fn(&[u8]) -> Result<D, String> + Send + Sync>),
 }
 
-enum WatchTask<T> {
+enum WatchTask<T, D> {
     Watch {
         opaque: T,
-        tp: FileType,
+        tp: FileType<D>,
         path: PathBuf,
-        result: OneSet<FileContent>,
+        result: OneSet<FileContent<D>>,
+    },
+    WatchDir {
+        opaque: T,
+        tp: FileType<D>,
+        path: PathBuf,
+        filter: Box<dyn Fn(&Path) -> bool + Send>,
     },
     Unwatch(PathBuf),
 }
 
-struct Watch<T> {
+struct Watch<T, D> {
     opaque: Vec<T>,
-    tp: FileType,
+    tp: FileType<D>,
     modified: std::time::SystemTime,
+    // `Some(root)` when the file was discovered under a recursively watched
+    // directory; such files are dropped from `tasks` once they disappear.
+    root: Option<PathBuf>,
+    // Whether the path currently exists, so Create/Write/Remove transitions can
+    // be computed even with the mtime fallback backend.
+    exists: bool,
+}
+
+// A recursively watched directory, retained so its filter can be re-applied to
+// files that appear after the initial bulk scan.
+struct Root<T, D> {
+    opaque: T,
+    tp: FileType<D>,
+    filter: Box<dyn Fn(&Path) -> bool + Send>,
 }
 
 
@@ -52,15 +123,23 @@ pub enum TryError {
     Closed,
 }
 
-pub struct FileWatcher<T: Clone + Send + 'static> {
-    sender: Option<crossbeam::channel::Sender<WatchTask<T>>>,
-    receiver: crossbeam::channel::Receiver<FileEvent<T>>,
+pub struct FileWatcher<T: Clone + Send + 'static, D: Clone + Send + 'static = ()> {
+    sender: Option<crossbeam::channel::Sender<WatchTask<T, D>>>,
+    receiver: crossbeam::channel::Receiver<FileEvent<T, D>>,
     handle: Option<std::thread::JoinHandle<()>>,
 }
 
 
-impl<T: Clone + Send + 'static> FileWatcher<T> {
-    pub fn new() -> FileWatcher<T> {
+impl<T: Clone + Send + 'static, D: Clone + Send + 'static> FileWatcher<T, D> {
+    pub fn new() -> FileWatcher<T, D> {
+        FileWatcher::with_debounce(std::time::Duration::from_millis(250))
+    }
+
+    /// Build a watcher that coalesces bursts of filesystem events: a path is
+    /// only re-read and reported once it has been quiet for `debounce`. This
+    /// collapses the write/truncate/rename storm of a single logical save into
+    /// one `FileEvent` and avoids reading a file mid-write.
+    pub fn with_debounce(debounce: std::time::Duration) -> FileWatcher<T, D> {
         let (tx,rx) = crossbeam::channel::unbounded();
         let (itx,irx) = crossbeam::channel::unbounded();
 
@@ -68,27 +147,13 @@ impl<T: Clone + Send + 'static> FileWatcher<T> {
             sender: Some(itx),
             receiver: rx,
             handle: Some(std::thread::Builder::new().name("file-watcher".to_string()).spawn(move || {
-                let mut inner = FileWatcherInner::new(tx);
-                loop {
-                    match irx.recv_timeout(inner.timeout) {
-                        Ok(task) => {
-                            inner.task(task);
-                            while let Ok(task) = irx.try_recv() {
-                                inner.task(task);
-                            }
-                        },
-                        Err(crossbeam::channel::RecvTimeoutError::Disconnected) => break,
-                        Err(crossbeam::channel::RecvTimeoutError::Timeout) => {},
-                    }
-
-                    // Process
-                    inner.check();
-                }
+                let mut inner = FileWatcherInner::new(tx,debounce);
+                inner.run(irx);
             }).unwrap()),
         }
     }
 
-    pub fn add_watch<P: Into<PathBuf>>(&self, tp: FileType, path: P, opaque: T) -> OneGet<FileContent> {
+    pub fn add_watch<P: Into<PathBuf>>(&self, tp: FileType<D>, path: P, opaque: T) -> OneGet<FileContent<D>> {
         let (os,og) = oneshot::oneshot();
         let task = WatchTask::Watch {
             opaque,
@@ -102,6 +167,22 @@ impl<T: Clone + Send + 'static> FileWatcher<T> {
         og
     }
 
+    pub fn add_watch_dir<P, F>(&self, tp: FileType<D>, path: P, opaque: T, filter: F)
+    where
+        P: Into<PathBuf>,
+        F: Fn(&Path) -> bool + Send + 'static,
+    {
+        let task = WatchTask::WatchDir {
+            opaque,
+            tp,
+            path: path.into(),
+            filter: Box::new(filter),
+        };
+        if let Some(sender) = &self.sender {
+            sender.send(task).ok();
+        }
+    }
+
     pub fn remove_watch<P: Into<PathBuf>>(&self, path: P) {
         let task = WatchTask::Unwatch(path.into());
         if let Some(sender) = &self.sender {
@@ -109,7 +190,7 @@ impl<T: Clone + Send + 'static> FileWatcher<T> {
         }
     }
 
-    pub fn try_recv(&self, timeout: Option<std::time::Duration>) -> Result<FileEvent<T>,TryError> {
+    pub fn try_recv(&self, timeout: Option<std::time::Duration>) -> Result<FileEvent<T, D>,TryError> {
         match timeout {
             Some(to) => self.receiver.recv_timeout(to).map_err(|e| match e {
                 crossbeam::channel::RecvTimeoutError::Disconnected => TryError::Closed,
@@ -123,7 +204,7 @@ impl<T: Clone + Send + 'static> FileWatcher<T> {
     }
 }
 
-impl<T: Clone + Send + 'static> Drop for FileWatcher<T> {
+impl<T: Clone + Send + 'static, D: Clone + Send + 'static> Drop for FileWatcher<T, D> {
     fn drop(&mut self) {
         log::info!("Terminating file-watcher");
         if let Some(sender) = self.sender.take() {
@@ -138,113 +219,465 @@ impl<T: Clone + Send + 'static> Drop for FileWatcher<T> {
     }
 }
 
-struct FileWatcherInner<T: Clone> {
-    sender: crossbeam::channel::Sender<FileEvent<T>>,
-    tasks: BTreeMap<PathBuf,Watch<T>>,
+struct FileWatcherInner<T: Clone, D: Clone> {
+    sender: crossbeam::channel::Sender<FileEvent<T, D>>,
+    tasks: BTreeMap<PathBuf,Watch<T, D>>,
+    roots: BTreeMap<PathBuf,Root<T, D>>,
+    // Per-path debounce deadlines: a change is only flushed once its deadline
+    // has passed without being refreshed by a newer event.
+    pending: BTreeMap<PathBuf,std::time::Instant>,
+    debounce: std::time::Duration,
     timeout: std::time::Duration,
+    last_sweep: std::time::Instant,
+    watcher: Option<RecommendedWatcher>,
+    events: crossbeam::channel::Receiver<PathBuf>,
 }
-impl<T: Clone> FileWatcherInner<T> {
-    fn new(sender: crossbeam::channel::Sender<FileEvent<T>>) -> FileWatcherInner<T>
+impl<T: Clone, D: Clone> FileWatcherInner<T, D> {
+    fn new(sender: crossbeam::channel::Sender<FileEvent<T, D>>, debounce: std::time::Duration) -> FileWatcherInner<T, D>
     {
+        // The notify watcher translates raw OS notifications into a stream of
+        // affected paths on this crossbeam channel; the background thread then
+        // re-reads each path rather than trusting the event kind.
+        let (etx,erx) = crossbeam::channel::unbounded();
+        let watcher = match RecommendedWatcher::new(
+            move |res: notify::Result<notify::Event>| {
+                if let Ok(ev) = res {
+                    for path in ev.paths {
+                        etx.send(path).ok();
+                    }
+                }
+            },
+            notify::Config::default(),
+        ) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                log::warn!("notify backend unavailable ({}), falling back to mtime polling", e);
+                None
+            },
+        };
+
         FileWatcherInner {
             sender,
-            timeout: std::time::Duration::new(1,0),
+            // With an event-driven backend the timeout is only a slow fallback
+            // sweep; without one it is the poll interval.
+            timeout: match watcher { Some(_) => std::time::Duration::new(60,0), None => std::time::Duration::new(1,0) },
             tasks: BTreeMap::new(),
+            roots: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            debounce,
+            last_sweep: std::time::Instant::now(),
+            watcher,
+            events: erx,
         }
     }
-    fn task(&mut self, task: WatchTask<T>) {
+
+    // The loop sleeps exactly as long as needed: until the nearest pending
+    // debounce deadline, but never longer than the remaining fallback-sweep
+    // interval.
+    fn next_timeout(&self) -> std::time::Duration {
+        let now = std::time::Instant::now();
+        let mut t = self.timeout.saturating_sub(now.saturating_duration_since(self.last_sweep));
+        if let Some(deadline) = self.pending.values().min() {
+            let rem = deadline.saturating_duration_since(now);
+            if rem < t {
+                t = rem;
+            }
+        }
+        t
+    }
+
+    // Emit every path whose debounce deadline has elapsed, and run the slow
+    // fallback sweep if its interval is up.
+    fn flush(&mut self) {
+        let now = std::time::Instant::now();
+        let due: Vec<PathBuf> = self.pending.iter()
+            .filter(|(_,d)| **d <= now)
+            .map(|(p,_)| p.clone())
+            .collect();
+        for path in due {
+            self.pending.remove(&path);
+            self.dispatch(&path);
+        }
+        if now.saturating_duration_since(self.last_sweep) >= self.timeout {
+            self.check();
+            self.last_sweep = now;
+        }
+    }
+
+    fn run(&mut self, irx: crossbeam::channel::Receiver<WatchTask<T, D>>) {
+        let events = self.events.clone();
+        loop {
+            crossbeam::channel::select! {
+                recv(irx) -> msg => match msg {
+                    Ok(task) => {
+                        self.task(task);
+                        while let Ok(task) = irx.try_recv() {
+                            self.task(task);
+                        }
+                    },
+                    Err(_) => break,
+                },
+                recv(events) -> msg => {
+                    if let Ok(path) = msg {
+                        self.notify(&path);
+                        while let Ok(path) = events.try_recv() {
+                            self.notify(&path);
+                        }
+                    }
+                },
+                default(self.next_timeout()) => {
+                    // Flush any debounced paths whose quiet window has elapsed,
+                    // plus the slow mtime-based fallback sweep when due.
+                    self.flush();
+                },
+            }
+        }
+    }
+
+    fn watch_path(&mut self, path: &Path) {
+        if let Some(w) = &mut self.watcher {
+            if let Err(e) = w.watch(path, RecursiveMode::NonRecursive) {
+                log::warn!("failed to watch {:?}: {}", path, e);
+            }
+        }
+    }
+
+    fn unwatch_path(&mut self, path: &Path) {
+        if let Some(w) = &mut self.watcher {
+            if let Err(e) = w.unwatch(path) {
+                log::warn!("failed to unwatch {:?}: {}", path, e);
+            }
+        }
+    }
+
+    fn task(&mut self, task: WatchTask<T, D>) {
         match task {
             WatchTask::Watch{ opaque, tp, path, result } => match self.tasks.get_mut(&path) {
                 Some(tsk) => {
                     tsk.opaque.push(opaque);
-                    let c = match fs::File::open(path) {
-                        Ok(fl) => match tp {
-                            FileType::Text => {
-                                let mut rdr = std::io::BufReader::new(fl);
-                                let mut s = String::new();
-                                match rdr.read_to_string(&mut s) {
-                                    Ok(_) => FileContent::Text(s),
-                                    Err(e) => e.into(),
-                                }
-                            },
-                        },
-                        Err(e) => e.into(),
-                    };
-                    result.set(c);
+                    result.set(read_content(&path,&tp));
                 },
                 None => {
                     if let Some(w) = FileWatcherInner::init(opaque,tp,result,&path) {
+                        self.watch_path(&path);
                         self.tasks.insert(path,w);
                     }
                 },
             },
+            WatchTask::WatchDir{ opaque, tp, path, filter } => {
+                self.watch_dir(opaque,tp,path,filter);
+            },
             WatchTask::Unwatch(path) => {
+                self.unwatch_path(&path);
                 self.tasks.remove(&path);
+                self.roots.remove(&path);
             },
         }
     }
 
-    fn init(opaque: T, tp: FileType, result: OneSet<FileContent>, path: &PathBuf) -> Option<Watch<T>> {
-        fn path_to_file(path: &PathBuf) -> Result<(std::time::SystemTime,fs::File),std::io::Error> {
-            let mtime = fs::metadata(path)?.modified()?;
-            let fl = fs::File::open(path)?;
-            Ok((mtime,fl))            
+    fn watch_dir(&mut self, opaque: T, tp: FileType<D>, root: PathBuf, filter: Box<dyn Fn(&Path) -> bool + Send>) {
+        // Watch the whole subtree recursively so files created or removed after
+        // the initial scan are picked up automatically.
+        if let Some(w) = &mut self.watcher {
+            if let Err(e) = w.watch(&root, RecursiveMode::Recursive) {
+                log::warn!("failed to watch dir {:?}: {}", root, e);
+            }
         }
 
-        let (w,c) = match path_to_file(path) {
-            Err(e) => (None,e.into()),
-            Ok((mtime,fl)) => {
-                match tp {
-                    FileType::Text => {
-                        let mut rdr = std::io::BufReader::new(fl);
-                        let mut s = String::new();
-                        match rdr.read_to_string(&mut s) {
-                            Ok(_) => (Some(Watch { opaque: vec![opaque], tp, modified: mtime }),FileContent::Text(s)),
-                            Err(e) => (None,e.into()),
-                        }
-                    },
+        // Initial bulk load: register and emit one event per matching file so
+        // the caller immediately sees the current state of the whole tree.
+        for entry in walkdir::WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+            let p = entry.path();
+            if entry.file_type().is_file() && filter(p) {
+                // Only announce a file we can actually track: if its mtime can't
+                // be read we can't register a `Watch`, and emitting a `Create`
+                // for an untracked path would strand the caller (every later
+                // edit/removal silently missed). Mirror `init`, which likewise
+                // tracks a file only when its metadata is readable.
+                if let Ok(mtime) = fs::metadata(p).and_then(|m| m.modified()) {
+                    self.tasks.insert(p.to_path_buf(), Watch {
+                        opaque: vec![opaque.clone()],
+                        tp: tp.clone(),
+                        modified: mtime,
+                        root: Some(root.clone()),
+                        exists: true,
+                    });
+                    let content = read_content(p,&tp);
+                    self.sender.send(FileEvent{ opaque: opaque.clone(), content, kind: ChangeKind::Create }).ok();
                 }
-            },
+            }
+        }
+
+        self.roots.insert(root.clone(), Root { opaque, tp, filter });
+    }
+
+    fn init(opaque: T, tp: FileType<D>, result: OneSet<FileContent<D>>, path: &PathBuf) -> Option<Watch<T, D>> {
+        let content = read_content(path,&tp);
+        // Track the file whenever it exists on disk, regardless of how it
+        // decoded: a present-but-undecodable file (a JSON/TOML config with a
+        // parse error, invalid UTF-8 under `Text`, or any non-`NotFound` read
+        // error) must still be watched so a later edit that fixes it re-emits.
+        // Only a file whose mtime cannot be read is left untracked: a genuinely
+        // absent file (`NotFound`), or one whose metadata is inaccessible.
+        let w = match fs::metadata(path).and_then(|m| m.modified()) {
+            Ok(mtime) => Some(Watch { opaque: vec![opaque], tp, modified: mtime, root: None, exists: true }),
+            Err(_) => None,
         };
-        result.set(c);
+        result.set(content);
         w
     }
 
-    fn check(&mut self) {
-        fn path_to_meta(path: &PathBuf) -> Result<std::time::SystemTime,std::io::Error> {
-            fs::metadata(path)?.modified()         
+    fn notify(&mut self, path: &Path) {
+        // Record (or refresh) a debounce deadline for any observed path that is
+        // tracked or belongs to a watched root; the actual read happens in
+        // `dispatch` once the path falls quiet.
+        if self.tasks.contains_key(path) || self.match_root(path).is_some() {
+            self.pending.insert(path.to_path_buf(), std::time::Instant::now() + self.debounce);
+        }
+    }
+
+    // Re-read a quiesced path. Because some platforms deliver `Create` where a
+    // `Write` is expected, we read the current contents on every event instead
+    // of trusting the event kind; a vanished file surfaces as an absent state.
+    fn dispatch(&mut self, path: &Path) {
+        if self.tasks.contains_key(path) {
+            self.emit_path(path);
+            return;
         }
-        
-        let mut to_remove = Vec::new();
+        // A path we are not yet tracking may belong to a recursively watched
+        // root; re-apply that root's filter and start tracking it.
+        if let Some((root,tp,opaque)) = self.match_root(path) {
+            if path.is_file() {
+                // As in the bulk scan, only announce the file once it is tracked.
+                if let Ok(mtime) = fs::metadata(path).and_then(|m| m.modified()) {
+                    self.tasks.insert(path.to_path_buf(), Watch {
+                        opaque: vec![opaque.clone()],
+                        tp: tp.clone(),
+                        modified: mtime,
+                        root: Some(root),
+                        exists: true,
+                    });
+                    let content = read_content(path,&tp);
+                    self.sender.send(FileEvent{ opaque, content, kind: ChangeKind::Create }).ok();
+                }
+            }
+        }
+    }
+
+    fn match_root(&self, path: &Path) -> Option<(PathBuf,FileType<D>,T)> {
+        for (root,r) in &self.roots {
+            if path.starts_with(root) && (r.filter)(path) {
+                return Some((root.clone(),r.tp.clone(),r.opaque.clone()));
+            }
+        }
+        None
+    }
+
+    fn emit_path(&mut self, path: &Path) {
+        if let Some(watch) = self.tasks.get_mut(path) {
+            let content = read_content(path,&watch.tp);
+            let absent = content.is_absent();
+            let present = content.is_present();
+            let kind = if absent {
+                ChangeKind::Remove
+            } else if present && !watch.exists {
+                ChangeKind::Create
+            } else {
+                ChangeKind::Write
+            };
+            if present { watch.exists = true; }
+            if absent { watch.exists = false; }
+            let from_root = watch.root.is_some();
+            if let Ok(mtime) = fs::metadata(path).and_then(|m| m.modified()) {
+                watch.modified = mtime;
+            }
+            for op in &watch.opaque {
+                self.sender.send(FileEvent{ opaque: op.clone(), content: content.clone(), kind }).ok();
+            }
+            // A file discovered under a root that has now vanished is dropped so
+            // a later re-creation is reported afresh.
+            if absent && from_root {
+                self.tasks.remove(path);
+            }
+        }
+    }
+
+    // Slow mtime-based fallback sweep for platforms where the notify backend is
+    // unavailable: route every path whose mtime advanced or that has disappeared
+    // into the same debounce pipeline as `notify`, so the sweep shares the one
+    // coalescing path rather than emitting directly. This keeps the debounce
+    // guarantee on the polling backend and prevents a double emit when a path
+    // already has a pending deadline.
+    fn check(&mut self) {
+        let mut changed = Vec::new();
         for (path,watch) in &mut self.tasks {
-            match path_to_meta(path) {
-                Ok(mtime) => match mtime > watch.modified {
-                    true => match fs::File::open(path) {
-                        Ok(fl) => match watch.tp {
-                            FileType::Text => {
-                                let mut rdr = std::io::BufReader::new(fl);
-                                let mut s = String::new();
-                                let event_cont = match rdr.read_to_string(&mut s) {
-                                    Ok(_) => {
-                                        watch.modified = mtime;                                        
-                                        FileContent::Text(s)
-                                    },
-                                    Err(e) => e.into(),
-                                };
-                                for op in &watch.opaque {                                    
-                                    self.sender.send(FileEvent{ opaque: op.clone(), content: event_cont.clone() }).ok();
-                                }
-                            },
-                        },
-                        Err(_) => to_remove.push(path.clone()),
-                    },
-                    false => continue,
+            match fs::metadata(path).and_then(|m| m.modified()) {
+                Ok(mtime) => {
+                    if mtime > watch.modified {
+                        changed.push(path.clone());
+                    }
+                },
+                // Only report a disappearance once; already-absent entries stay
+                // quiet until they reappear.
+                Err(_) => {
+                    if watch.exists {
+                        changed.push(path.clone());
+                    }
                 },
-                Err(_) => to_remove.push(path.clone()),
             }
         }
-        for path in to_remove {
-            self.tasks.remove(&path);
+        let deadline = std::time::Instant::now() + self.debounce;
+        for path in changed {
+            self.pending.insert(path, deadline);
+        }
+    }
+}
+
+fn read_content<D>(path: &Path, tp: &FileType<D>) -> FileContent<D> {
+    match fs::read(path) {
+        Ok(buf) => match tp {
+            FileType::Text => match String::from_utf8(buf) {
+                Ok(s) => FileContent::SingleFile(Some(s)),
+                Err(e) => FileContent::Error(e.to_string()),
+            },
+            FileType::Bytes => FileContent::Bytes(Some(buf)),
+            FileType::Custom(decode) => match decode(&buf) {
+                Ok(d) => FileContent::Decoded(Some(d)),
+                Err(e) => FileContent::Error(e),
+            },
+        },
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => match tp {
+            FileType::Text => FileContent::SingleFile(None),
+            FileType::Bytes => FileContent::Bytes(None),
+            FileType::Custom(_) => FileContent::Decoded(None),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique_path(tag: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!("runtime-config-test-{}-{}", tag, std::process::id()));
+        p
+    }
+
+    fn drain<T: Clone, D: Clone>(rx: &crossbeam::channel::Receiver<FileEvent<T, D>>) -> Vec<FileEvent<T, D>> {
+        let mut out = Vec::new();
+        while let Ok(ev) = rx.try_recv() { out.push(ev); }
+        out
+    }
+
+    // write→write for the same path inside one debounce window collapses to a
+    // single event carrying the newest contents: replaying it reconstructs what
+    // is on disk now, not any intermediate write.
+    #[test]
+    fn coalesces_write_write_to_newest() {
+        let path = unique_path("write-write");
+        fs::write(&path, b"first").unwrap();
+
+        let (tx,rx) = crossbeam::channel::unbounded();
+        let mut inner = FileWatcherInner::new(tx, std::time::Duration::from_millis(10));
+        inner.tasks.insert(path.clone(), Watch {
+            opaque: vec![()],
+            tp: FileType::Text,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            root: None,
+            exists: true,
+        });
+
+        fs::write(&path, b"second").unwrap();
+        inner.notify(&path);
+        fs::write(&path, b"third").unwrap();
+        inner.notify(&path);
+
+        // Two notifications, one pending deadline: the read happens once, after
+        // the window falls quiet.
+        assert_eq!(inner.pending.len(), 1);
+        std::thread::sleep(std::time::Duration::from_millis(15));
+        inner.flush();
+
+        let events = drain(&rx);
+        assert_eq!(events.len(), 1);
+        match &events[0].content {
+            FileContent::SingleFile(Some(s)) => assert_eq!(s, "third"),
+            other => panic!("expected newest contents, got {:?}", other),
+        }
+
+        fs::remove_file(&path).ok();
+    }
+
+    // create→delete for the same path inside one debounce window collapses to a
+    // single `Remove`: replaying it reconstructs the now-absent file on disk.
+    #[test]
+    fn coalesces_create_delete_to_remove() {
+        let path = unique_path("create-delete");
+        fs::remove_file(&path).ok();
+
+        let (tx,rx) = crossbeam::channel::unbounded();
+        let mut inner = FileWatcherInner::new(tx, std::time::Duration::from_millis(10));
+        inner.tasks.insert(path.clone(), Watch {
+            opaque: vec![()],
+            tp: FileType::Text,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            root: None,
+            exists: false,
+        });
+
+        fs::write(&path, b"transient").unwrap();
+        inner.notify(&path);
+        fs::remove_file(&path).unwrap();
+        inner.notify(&path);
+
+        assert_eq!(inner.pending.len(), 1);
+        std::thread::sleep(std::time::Duration::from_millis(15));
+        inner.flush();
+
+        let events = drain(&rx);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].content, FileContent::SingleFile(None)));
+        assert_eq!(events[0].kind, ChangeKind::Remove);
+    }
+
+    // The mtime fallback sweep feeds the same debounce pipeline, so a file that
+    // appears between sweeps yields a single `Create` once its window falls
+    // quiet — never one event from `check()` and another from the later flush.
+    #[test]
+    fn check_coalesces_through_pending() {
+        let path = unique_path("check-create");
+        fs::remove_file(&path).ok();
+
+        let (tx,rx) = crossbeam::channel::unbounded();
+        let mut inner = FileWatcherInner::new(tx, std::time::Duration::from_millis(10));
+        inner.tasks.insert(path.clone(), Watch {
+            opaque: vec![()],
+            tp: FileType::Text,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            root: None,
+            exists: false,
+        });
+
+        fs::write(&path, b"appeared").unwrap();
+
+        // The sweep only schedules a deadline; it must not emit directly.
+        inner.check();
+        assert_eq!(inner.pending.len(), 1);
+        assert!(drain(&rx).is_empty());
+
+        std::thread::sleep(std::time::Duration::from_millis(15));
+        inner.flush();
+
+        let events = drain(&rx);
+        assert_eq!(events.len(), 1);
+        match &events[0].content {
+            FileContent::SingleFile(Some(s)) => assert_eq!(s, "appeared"),
+            other => panic!("expected contents, got {:?}", other),
         }
+        assert_eq!(events[0].kind, ChangeKind::Create);
+
+        fs::remove_file(&path).ok();
     }
 }